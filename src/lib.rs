@@ -16,17 +16,26 @@
 //!
 //!
 //! # Implementing GlobalAlloc
-//! See the [global alloc](https://github.com/gz/rust-slabmalloc/tree/master/examples/global_alloc.rs) example.
-#![allow(unused_features)]
-#![cfg_attr(test, feature(prelude_import, test, c_void_variant, core_intrinsics))]
+//! [`LockedHeap`] wraps a `ZoneAllocator` behind a spinlock and refills it
+//! from a user-supplied [`PageSource`] on demand, so it can be used directly
+//! as a `#[global_allocator]`. See the [global alloc](https://github.com/gz/rust-slabmalloc/tree/master/examples/global_alloc.rs)
+//! example for the lower-level, hand-wired alternative.
 #![no_std]
 #![crate_name = "slabmalloc"]
 #![crate_type = "lib"]
 
+mod locked_heap;
+#[cfg(all(feature = "std", unix))]
+mod mmap;
+mod page_source;
 mod pages;
 mod sc;
 mod zone;
 
+pub use locked_heap::*;
+#[cfg(all(feature = "std", unix))]
+pub use mmap::*;
+pub use page_source::*;
 pub use pages::*;
 pub use sc::*;
 pub use zone::*;
@@ -34,18 +43,12 @@ pub use zone::*;
 #[cfg(test)]
 #[macro_use]
 extern crate std;
-#[cfg(test)]
-extern crate test;
 
 #[cfg(test)]
 mod tests;
 
 use core::alloc::Layout;
-use core::fmt;
-use core::mem;
-use core::ptr::{self, NonNull};
-
-use log::trace;
+use core::ptr::NonNull;
 
 /// How many bytes in the page are used by allocator meta-data.
 const OBJECT_PAGE_METADATA_OVERHEAD: usize = 80;
@@ -70,26 +73,58 @@ pub enum AllocationError {
 
 /// Allocator trait to be implemented by users of slabmalloc to provide memory to slabmalloc.
 ///
+/// A `Layout` with `size() == 0` never consumes a slot: `allocate` returns a
+/// dangling pointer aligned to `layout.align()` without touching any page,
+/// and `deallocate` is a no-op for it, matching the usual Rust allocator
+/// convention for zero-sized types.
+///
 /// # Safety
 /// Needs to adhere to safety requirements of a rust allocator (see GlobalAlloc et. al.).
 pub unsafe trait Allocator<'a> {
     fn allocate(&mut self, layout: Layout) -> Result<NonNull<u8>, AllocationError>;
     fn deallocate(&mut self, ptr: NonNull<u8>, layout: Layout) -> Result<(), AllocationError>;
 
-    /// Refill the allocator with a [`LargeObjectPage`].
-    //
+    /// Returns the number of bytes actually backing an allocation of `layout`,
+    /// i.e. the size of the size-class slot `allocate` would hand out.
+    ///
+    /// This is always `>= layout.size()` and is stable for a given size-class,
+    /// so it is safe to use the extra space: `deallocate` accepts any `Layout`
+    /// whose size falls within the same class as the one originally passed to
+    /// `allocate`/`allocate_with_size`.
+    fn allocated_size(&self, layout: Layout) -> usize;
+
+    /// Like [`Allocator::allocate`], but also returns how many bytes of the
+    /// returned slot are actually usable (see [`Allocator::allocated_size`]).
+    /// Callers that can grow into unused slack (e.g. a hash table growing its
+    /// backing array) should prefer this over `allocate`.
+    fn allocate_with_size(
+        &mut self,
+        layout: Layout,
+    ) -> Result<(NonNull<u8>, usize), AllocationError> {
+        let size = self.allocated_size(layout);
+        self.allocate(layout).map(|ptr| (ptr, size))
+    }
+
+    /// Manually refill the allocator with a [`LargeObjectPage`].
+    ///
+    /// Only needed when the allocator has no [`PageSource`]; a `ZoneAllocator`
+    /// created via [`ZoneAllocator::with_page_source`] refills itself.
+    ///
     /// # Safety
-    /// TBD (this API needs to change anyways, likely new page should be a raw pointer)
+    /// `new_page` must outlive every allocation handed out of it.
     unsafe fn refill_large(
         &mut self,
         layout: Layout,
         new_page: &'a mut LargeObjectPage<'a>,
     ) -> Result<(), AllocationError>;
 
-    /// Refill the allocator with a [`ObjectPage`].
+    /// Manually refill the allocator with a [`ObjectPage`].
+    ///
+    /// Only needed when the allocator has no [`PageSource`]; a `ZoneAllocator`
+    /// created via [`ZoneAllocator::with_page_source`] refills itself.
     ///
     /// # Safety
-    /// TBD (this API needs to change anyways, likely new page should be a raw pointer)
+    /// `new_page` must outlive every allocation handed out of it.
     unsafe fn refill(
         &mut self,
         layout: Layout,
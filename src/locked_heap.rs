@@ -0,0 +1,102 @@
+//! A ready-to-use `#[global_allocator]` built on top of `ZoneAllocator`.
+//!
+//! The `global_alloc.rs` example shows how to wire up a global allocator by
+//! hand: lock a `ZoneAllocator`, and on `OutOfMemory` go fetch a fresh page
+//! and retry. `LockedHeap` packages that exact pattern into a single type so
+//! callers only have to supply a [`PageSource`]:
+//!
+//! ```ignore
+//! #[global_allocator]
+//! static ALLOCATOR: LockedHeap<MyPageSource> = LockedHeap::new(MyPageSource::new());
+//! ```
+
+use core::alloc::{GlobalAlloc, Layout};
+use core::ptr::{self, NonNull};
+
+use spin::Mutex;
+
+use crate::{
+    AllocationError, Allocator, LargeObjectPage, ObjectPage, PageSource, ZoneAllocator,
+    LARGE_OBJECT_PAGE_SIZE,
+};
+
+/// A [`GlobalAlloc`] implementation backed by a `ZoneAllocator`, guarded by a
+/// spinlock and refilled on demand from a `PageSource`.
+///
+/// `pager` is driven from `alloc`/`dealloc` directly rather than handed to
+/// `ZoneAllocator::with_page_source`: the zone would need a `&'static dyn
+/// PageSource` pointing back into this same struct, which isn't sound for a
+/// type callers are free to move.
+pub struct LockedHeap<P: PageSource> {
+    zone: Mutex<ZoneAllocator<'static>>,
+    pager: P,
+}
+
+unsafe impl<P: PageSource> Sync for LockedHeap<P> {}
+
+impl<P: PageSource> LockedHeap<P> {
+    pub const fn new(pager: P) -> LockedHeap<P> {
+        LockedHeap {
+            zone: Mutex::new(ZoneAllocator::new()),
+            pager,
+        }
+    }
+
+    /// Pulls a fresh page from `self.pager` sized for `layout` and refills
+    /// `zone` with it, so the next allocation attempt can succeed.
+    fn refill(
+        &self,
+        zone: &mut ZoneAllocator<'static>,
+        layout: Layout,
+    ) -> Result<(), AllocationError> {
+        let page_size = ZoneAllocator::page_size_for(layout);
+        let page = self
+            .pager
+            .allocate_page(page_size)
+            .ok_or(AllocationError::OutOfMemory)?;
+
+        if page_size == LARGE_OBJECT_PAGE_SIZE {
+            unsafe { zone.refill_large(layout, &mut *(page.as_ptr() as *mut LargeObjectPage)) }
+        } else {
+            unsafe { zone.refill(layout, &mut *(page.as_ptr() as *mut ObjectPage)) }
+        }
+    }
+}
+
+unsafe impl<P: PageSource> GlobalAlloc for LockedHeap<P> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        // `ZoneAllocator` can't back anything bigger than
+        // `LARGE_OBJECT_PAGE_SIZE`; fall through to the pager directly for
+        // those instead of going through the zone at all.
+        if layout.size() > LARGE_OBJECT_PAGE_SIZE {
+            return self
+                .pager
+                .allocate_oversized(layout)
+                .map_or(ptr::null_mut(), |p| p.as_ptr());
+        }
+
+        let mut zone = self.zone.lock();
+        match zone.allocate(layout) {
+            Ok(ptr) => ptr.as_ptr(),
+            Err(AllocationError::InvalidLayout) => ptr::null_mut(),
+            Err(AllocationError::OutOfMemory) => {
+                if self.refill(&mut zone, layout).is_err() {
+                    return ptr::null_mut();
+                }
+                zone.allocate(layout).map_or(ptr::null_mut(), |p| p.as_ptr())
+            }
+        }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        // Mirrors `alloc`: anything this large came straight from the pager.
+        if layout.size() > LARGE_OBJECT_PAGE_SIZE {
+            self.pager
+                .release_oversized(NonNull::new_unchecked(ptr), layout);
+            return;
+        }
+
+        let mut zone = self.zone.lock();
+        let _ = zone.deallocate(NonNull::new_unchecked(ptr), layout);
+    }
+}
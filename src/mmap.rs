@@ -0,0 +1,110 @@
+//! A [`PageSource`] that serves pages via `mmap`/`munmap`.
+//!
+//! Gated behind the `std` feature (it needs `libc`) and `unix` (it needs
+//! `libc::mmap`), so it is only built for hosted targets that have them.
+
+use core::alloc::Layout;
+use core::ptr::{self, NonNull};
+
+use libc::c_void;
+
+use crate::{PageSource, LARGE_OBJECT_PAGE_SIZE, OBJECT_PAGE_SIZE};
+
+#[cfg(target_os = "linux")]
+const HUGETLB_FLAG: i32 = libc::MAP_HUGETLB;
+#[cfg(not(target_os = "linux"))]
+const HUGETLB_FLAG: i32 = 0;
+
+/// Serves pages via anonymous `mmap`. `LARGE_OBJECT_PAGE_SIZE` requests try
+/// `MAP_HUGETLB` first (Linux only): the kernel hands back memory already
+/// aligned to the huge page size. If that's unavailable (the default on
+/// non-Linux, and whenever no huge pages are reserved on Linux), pages fall
+/// back to [`Self::mmap_aligned`], since plain anonymous `mmap` is only
+/// specified to return regular-page-aligned memory, not aligned to whatever
+/// `size` was requested.
+pub struct MmapPageSource;
+
+impl Default for MmapPageSource {
+    fn default() -> MmapPageSource {
+        MmapPageSource::new()
+    }
+}
+
+impl MmapPageSource {
+    pub const fn new() -> MmapPageSource {
+        MmapPageSource
+    }
+
+    fn mmap(size: usize, extra_flags: i32) -> Option<NonNull<u8>> {
+        let addr = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                size,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANON | extra_flags,
+                -1,
+                0,
+            )
+        };
+        if addr == libc::MAP_FAILED {
+            None
+        } else {
+            NonNull::new(addr as *mut u8)
+        }
+    }
+
+    /// Maps `size` bytes aligned to `align`, by over-mapping `size + align`
+    /// bytes and trimming the unaligned head/tail back off — the same
+    /// technique jemalloc/mimalloc use to get an arbitrary alignment out of
+    /// an allocator (`mmap`) that only promises regular-page alignment.
+    /// `align` need not be `>=` the system page size; any excess head/tail is
+    /// simply a partial page or two, still valid to `munmap` on its own.
+    fn mmap_aligned(size: usize, align: usize) -> Option<NonNull<u8>> {
+        let oversized = size.checked_add(align)?;
+        let base = Self::mmap(oversized, 0)?.as_ptr() as usize;
+        let aligned = (base + align - 1) & !(align - 1);
+
+        let head = aligned - base;
+        if head > 0 {
+            unsafe { libc::munmap(base as *mut c_void, head) };
+        }
+        let tail = oversized - head - size;
+        if tail > 0 {
+            unsafe { libc::munmap((aligned + size) as *mut c_void, tail) };
+        }
+        NonNull::new(aligned as *mut u8)
+    }
+}
+
+unsafe impl PageSource for MmapPageSource {
+    fn allocate_page(&self, size: usize) -> Option<NonNull<u8>> {
+        if size == LARGE_OBJECT_PAGE_SIZE {
+            if HUGETLB_FLAG != 0 {
+                if let Some(page) = Self::mmap(size, HUGETLB_FLAG) {
+                    return Some(page);
+                }
+            }
+            Self::mmap_aligned(size, size)
+        } else {
+            Self::mmap(size, 0)
+        }
+    }
+
+    fn release_page(&self, ptr: NonNull<u8>, size: usize) {
+        let result = unsafe { libc::munmap(ptr.as_ptr() as *mut c_void, size) };
+        assert_eq!(result, 0, "munmap failed");
+    }
+
+    fn allocate_oversized(&self, layout: Layout) -> Option<NonNull<u8>> {
+        if layout.align() <= OBJECT_PAGE_SIZE {
+            Self::mmap(layout.size(), 0)
+        } else {
+            Self::mmap_aligned(layout.size(), layout.align())
+        }
+    }
+
+    fn release_oversized(&self, ptr: NonNull<u8>, layout: Layout) {
+        let result = unsafe { libc::munmap(ptr.as_ptr() as *mut c_void, layout.size()) };
+        assert_eq!(result, 0, "munmap failed");
+    }
+}
@@ -0,0 +1,49 @@
+//! A trait for providers of backing memory that `SCAllocator`/`ZoneAllocator`
+//! can pull fresh pages from once they run out of room.
+
+use core::alloc::Layout;
+use core::ptr::NonNull;
+
+/// Supplies the pages a [`crate::SCAllocator`]/[`crate::ZoneAllocator`]
+/// refills itself with once it runs out of room, and takes pages back once
+/// they are fully empty.
+///
+/// `SCAllocator`/`ZoneAllocator` hold a `&dyn PageSource` and are themselves
+/// `Send`, which means that reference can end up read from whatever thread
+/// the allocator is moved to while the original owner still holds it too;
+/// requiring `Sync` here is what makes that sound.
+///
+/// # Safety
+/// `allocate_page(size)` must return memory valid for at least `size` bytes
+/// and aligned to `size` (the memory is cast directly into an
+/// `ObjectPage`/`LargeObjectPage`).
+pub unsafe trait PageSource: Sync {
+    /// Allocates a page of exactly `size` bytes (`OBJECT_PAGE_SIZE` or
+    /// `LARGE_OBJECT_PAGE_SIZE`), or `None` if none are available.
+    fn allocate_page(&self, size: usize) -> Option<NonNull<u8>>;
+    /// Returns a page previously obtained from `allocate_page` back to the source.
+    fn release_page(&self, ptr: NonNull<u8>, size: usize);
+
+    /// Allocates memory for a `layout` too large for `ZoneAllocator` to back
+    /// (i.e. bigger than `LARGE_OBJECT_PAGE_SIZE`), bypassing the zone/page
+    /// machinery entirely. Unlike `allocate_page`, the returned memory only
+    /// has to satisfy `layout`'s own size and alignment, not be sized/aligned
+    /// to a fixed page size.
+    ///
+    /// Returns `None` by default: most `PageSource`s only ever have to serve
+    /// the two fixed page sizes `allocate_page` does, and have no sensible
+    /// way to satisfy an arbitrarily large, arbitrarily aligned request.
+    fn allocate_oversized(&self, layout: Layout) -> Option<NonNull<u8>> {
+        let _ = layout;
+        None
+    }
+
+    /// Returns memory previously obtained from `allocate_oversized` back to
+    /// the source. Only called with a `layout` that `allocate_oversized`
+    /// itself previously returned `Some` for, so the default (empty) body is
+    /// never reached unless a source overrides `allocate_oversized` without
+    /// also overriding this.
+    fn release_oversized(&self, ptr: NonNull<u8>, layout: Layout) {
+        let _ = (ptr, layout);
+    }
+}
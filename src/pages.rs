@@ -0,0 +1,166 @@
+//! Types that implement [`AllocablePage`] and the metadata attached to them.
+//!
+//! An `AllocablePage` is a (typically page-sized) piece of memory that is
+//! carved up into equally sized objects by a [`crate::SCAllocator`]. Two
+//! default implementations are provided: [`ObjectPage`] (4 KiB) and
+//! [`LargeObjectPage`] (2 MiB).
+
+use core::fmt;
+use core::mem;
+use core::ptr::NonNull;
+
+use crate::{VAddr, OBJECT_PAGE_METADATA_OVERHEAD, OBJECT_PAGE_SIZE};
+
+/// Number of `u64` words we use to track allocated/free objects in a page's
+/// bitfield (one bit per object-slot).
+const BITFIELD_WORDS: usize = 8;
+
+/// A trait implemented by `ObjectPage` and `LargeObjectPage` that allows
+/// `SCAllocator` to allocate objects from, and return objects into, the page.
+pub trait AllocablePage {
+    /// The total size in bytes of the page (used to compute capacity).
+    const SIZE: usize;
+
+    /// Tracks the currently allocated objects: a set bit means the
+    /// corresponding object-slot is in use.
+    fn bitfield(&self) -> &[u64; BITFIELD_WORDS];
+    fn bitfield_mut(&mut self) -> &mut [u64; BITFIELD_WORDS];
+
+    /// Intrusive link to the next page of the same size-class.
+    fn next(&self) -> &Option<NonNull<u8>>;
+    fn next_mut(&mut self) -> &mut Option<NonNull<u8>>;
+
+    /// Tries to find a free object-slot within the page that is at least
+    /// `layout_size` bytes and satisfies `layout_align`, returning the
+    /// index and the address of that slot.
+    fn first_fit(&self, layout_size: usize, layout_align: usize) -> Option<(usize, usize)> {
+        let base_addr = self.vaddr();
+        let capacity = Self::SIZE - OBJECT_PAGE_METADATA_OVERHEAD;
+        let obj_per_page = capacity / layout_size;
+
+        for idx in 0..obj_per_page {
+            if !self.is_allocated(idx) {
+                let offset = idx * layout_size;
+                let addr = base_addr + offset;
+                if addr.is_multiple_of(layout_align) {
+                    return Some((idx, addr));
+                }
+            }
+        }
+        None
+    }
+
+    fn is_allocated(&self, idx: usize) -> bool {
+        let word = idx / 64;
+        let bit = idx % 64;
+        self.bitfield()[word] & (1 << bit) != 0
+    }
+
+    fn set_allocated(&mut self, idx: usize) {
+        let word = idx / 64;
+        let bit = idx % 64;
+        self.bitfield_mut()[word] |= 1 << bit;
+    }
+
+    fn clear_allocated(&mut self, idx: usize) {
+        let word = idx / 64;
+        let bit = idx % 64;
+        self.bitfield_mut()[word] &= !(1 << bit);
+    }
+
+    /// True if no object-slot in this page is currently allocated.
+    fn is_empty(&self) -> bool {
+        self.bitfield().iter().all(|w| *w == 0)
+    }
+
+    /// True if every object-slot in this page is currently allocated, given
+    /// there are `obj_per_page` total slots.
+    fn is_full(&self, obj_per_page: usize) -> bool {
+        (0..obj_per_page).all(|idx| self.is_allocated(idx))
+    }
+
+    /// The virtual address of the start of this page.
+    fn vaddr(&self) -> VAddr {
+        self as *const Self as *const u8 as VAddr
+    }
+}
+
+/// A 4 KiB page that can be used to allocate objects smaller than
+/// `OBJECT_PAGE_SIZE - OBJECT_PAGE_METADATA_OVERHEAD`.
+#[repr(align(4096))]
+pub struct ObjectPage<'a> {
+    // Never read directly; reserves the page's object-storage space so the
+    // struct's size (and thus `obj_per_page`'s arithmetic) matches `SIZE`.
+    #[allow(dead_code)]
+    data: [u8; OBJECT_PAGE_SIZE - OBJECT_PAGE_METADATA_OVERHEAD],
+    bitfield: [u64; BITFIELD_WORDS],
+    next: Option<NonNull<u8>>,
+    _marker: core::marker::PhantomData<&'a ()>,
+}
+
+impl<'a> fmt::Debug for ObjectPage<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ObjectPage {{ bitfield: {:?} }}", self.bitfield)
+    }
+}
+
+impl<'a> AllocablePage for ObjectPage<'a> {
+    const SIZE: usize = OBJECT_PAGE_SIZE;
+
+    fn bitfield(&self) -> &[u64; BITFIELD_WORDS] {
+        &self.bitfield
+    }
+
+    fn bitfield_mut(&mut self) -> &mut [u64; BITFIELD_WORDS] {
+        &mut self.bitfield
+    }
+
+    fn next(&self) -> &Option<NonNull<u8>> {
+        &self.next
+    }
+
+    fn next_mut(&mut self) -> &mut Option<NonNull<u8>> {
+        &mut self.next
+    }
+}
+
+/// A 2 MiB page that can be used to allocate objects smaller than
+/// `LARGE_OBJECT_PAGE_SIZE - OBJECT_PAGE_METADATA_OVERHEAD`.
+#[repr(align(2097152))]
+pub struct LargeObjectPage<'a> {
+    // Never read directly; reserves the page's object-storage space so the
+    // struct's size (and thus `obj_per_page`'s arithmetic) matches `SIZE`.
+    #[allow(dead_code)]
+    data: [u8; crate::LARGE_OBJECT_PAGE_SIZE - OBJECT_PAGE_METADATA_OVERHEAD],
+    bitfield: [u64; BITFIELD_WORDS],
+    next: Option<NonNull<u8>>,
+    _marker: core::marker::PhantomData<&'a ()>,
+}
+
+impl<'a> fmt::Debug for LargeObjectPage<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "LargeObjectPage {{ bitfield: {:?} }}", self.bitfield)
+    }
+}
+
+impl<'a> AllocablePage for LargeObjectPage<'a> {
+    const SIZE: usize = crate::LARGE_OBJECT_PAGE_SIZE;
+
+    fn bitfield(&self) -> &[u64; BITFIELD_WORDS] {
+        &self.bitfield
+    }
+
+    fn bitfield_mut(&mut self) -> &mut [u64; BITFIELD_WORDS] {
+        &mut self.bitfield
+    }
+
+    fn next(&self) -> &Option<NonNull<u8>> {
+        &self.next
+    }
+
+    fn next_mut(&mut self) -> &mut Option<NonNull<u8>> {
+        &mut self.next
+    }
+}
+
+const _: () = assert!(mem::size_of::<ObjectPage>() <= OBJECT_PAGE_SIZE);
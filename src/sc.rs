@@ -0,0 +1,201 @@
+//! A `SCAllocator` allocates objects of a single, fixed size.
+
+use core::alloc::Layout;
+use core::mem;
+use core::ptr::NonNull;
+
+use log::trace;
+
+use crate::{AllocablePage, AllocationError, LargeObjectPage, ObjectPage, PageSource};
+
+/// An allocator for a single size-class, backed by one or more
+/// [`AllocablePage`]s.
+///
+/// Pages are kept on three intrusive, singly-linked lists threaded through
+/// `AllocablePage::next`, mirroring the usual slab-allocator partial/full/empty
+/// split: `partial` pages have at least one free slot and are where
+/// `allocate` looks first, `full` pages have none, and `empty` pages have
+/// none allocated. Without a [`PageSource`], a page that goes empty is kept
+/// on `empty` and reused by the next refill; once a `SCAllocator` has a
+/// `PageSource`, a page is returned to it as soon as it goes empty rather
+/// than being kept on `empty`.
+pub struct SCAllocator<'a, P: AllocablePage> {
+    /// Size of the objects this allocator hands out (in bytes).
+    pub(crate) size: usize,
+    /// Alignment of the objects this allocator hands out (in bytes).
+    pub(crate) alignment: usize,
+    /// How many objects fit into a single page.
+    pub(crate) obj_per_page: usize,
+    /// Pages that still have at least one free slot.
+    partial: Option<NonNull<P>>,
+    /// Pages with no objects allocated. Only filled while there is no
+    /// `pager`; `refill_partial` reuses them before asking `pager` for more.
+    empty: Option<NonNull<P>>,
+    /// Pages with every slot allocated.
+    full: Option<NonNull<P>>,
+    /// Where to pull a fresh backing page from once `partial` and `empty`
+    /// are both exhausted. `None` means callers must `insert_page` manually.
+    pager: Option<&'a dyn PageSource>,
+    _marker: core::marker::PhantomData<&'a ()>,
+}
+
+unsafe impl<'a, P: AllocablePage> Send for SCAllocator<'a, P> {}
+
+impl<'a, P: AllocablePage + 'a> SCAllocator<'a, P> {
+    /// Creates a new, empty allocator for objects of `size` bytes.
+    pub const fn new(size: usize) -> SCAllocator<'a, P> {
+        SCAllocator {
+            size,
+            alignment: mem::align_of::<usize>(),
+            obj_per_page: (P::SIZE - crate::OBJECT_PAGE_METADATA_OVERHEAD) / size,
+            partial: None,
+            empty: None,
+            full: None,
+            pager: None,
+            _marker: core::marker::PhantomData,
+        }
+    }
+
+    /// Makes this allocator self-sufficient: once `partial` and `empty` are
+    /// both exhausted, `allocate` pulls a fresh page from `pager` instead of
+    /// returning `OutOfMemory`, and a page is handed back to `pager` as soon
+    /// as it goes empty.
+    pub fn set_page_source(&mut self, pager: &'a dyn PageSource) {
+        self.pager = Some(pager);
+    }
+
+    /// Adds a new page to this allocator's partial list.
+    ///
+    /// # Safety
+    /// `new_page` must outlive every allocation handed out of it.
+    pub unsafe fn insert_page(&mut self, new_page: &'a mut P) {
+        Self::push(&mut self.partial, new_page);
+        trace!("SCAllocator({}) inserted new page", self.size);
+    }
+
+    fn push(list: &mut Option<NonNull<P>>, page: &'a mut P) {
+        *page.next_mut() = list.map(|p| p.cast());
+        *list = Some(NonNull::from(page));
+    }
+
+    fn pop(list: &mut Option<NonNull<P>>) -> Option<NonNull<P>> {
+        let mut head = (*list)?;
+        let head_ref = unsafe { head.as_mut() };
+        *list = head_ref.next().map(|p| p.cast());
+        *head_ref.next_mut() = None;
+        Some(head)
+    }
+
+    /// Removes `target` from `list`, wherever in the list it currently is.
+    fn remove(list: &mut Option<NonNull<P>>, target: NonNull<P>) {
+        let target_next = unsafe { *target.as_ref().next() };
+        if *list == Some(target) {
+            *list = target_next.map(|p| p.cast());
+            return;
+        }
+        let mut cur = *list;
+        while let Some(mut node) = cur {
+            let node_ref = unsafe { node.as_mut() };
+            let next = node_ref.next().map(|p| p.cast::<P>());
+            if next == Some(target) {
+                *node_ref.next_mut() = target_next;
+                return;
+            }
+            cur = next;
+        }
+    }
+
+    fn find_containing(list: Option<NonNull<P>>, addr: usize) -> Option<NonNull<P>> {
+        let mut cur = list;
+        while let Some(page_ptr) = cur {
+            let page = unsafe { page_ptr.as_ref() };
+            let start = page_ptr.as_ptr() as usize;
+            if addr >= start && addr < start + P::SIZE {
+                return Some(page_ptr);
+            }
+            cur = page.next().map(|p| p.cast());
+        }
+        None
+    }
+
+    /// Moves a page onto `partial`, pulling one from `empty` if there is
+    /// one, otherwise requesting a fresh one from `pager`.
+    fn refill_partial(&mut self) -> Result<(), AllocationError> {
+        if let Some(page) = Self::pop(&mut self.empty) {
+            Self::push(&mut self.partial, unsafe { &mut *page.as_ptr() });
+            return Ok(());
+        }
+        let pager = self.pager.ok_or(AllocationError::OutOfMemory)?;
+        let raw = pager
+            .allocate_page(P::SIZE)
+            .ok_or(AllocationError::OutOfMemory)?;
+        let page: &'a mut P = unsafe { &mut *(raw.as_ptr() as *mut P) };
+        *page.next_mut() = None;
+        Self::push(&mut self.partial, page);
+        Ok(())
+    }
+
+    /// Tries to allocate a single object that fits `layout`.
+    pub fn allocate(&mut self, layout: Layout) -> Result<NonNull<u8>, AllocationError> {
+        if self.partial.is_none() {
+            self.refill_partial()?;
+        }
+
+        let mut page_ptr = self.partial.ok_or(AllocationError::OutOfMemory)?;
+        let page = unsafe { page_ptr.as_mut() };
+        let (idx, addr) = page
+            .first_fit(self.size, layout.align().max(self.alignment))
+            .ok_or(AllocationError::OutOfMemory)?;
+        page.set_allocated(idx);
+
+        if page.is_full(self.obj_per_page) {
+            Self::remove(&mut self.partial, page_ptr);
+            Self::push(&mut self.full, unsafe { &mut *page_ptr.as_ptr() });
+        }
+
+        trace!("SCAllocator({}) allocated object at {:#x}", self.size, addr);
+        Ok(unsafe { NonNull::new_unchecked(addr as *mut u8) })
+    }
+
+    /// Returns `ptr` to the page it was allocated from.
+    pub fn deallocate(
+        &mut self,
+        ptr: NonNull<u8>,
+        _layout: Layout,
+    ) -> Result<(), AllocationError> {
+        let addr = ptr.as_ptr() as usize;
+
+        let (mut page_ptr, was_full) = if let Some(p) = Self::find_containing(self.full, addr) {
+            (p, true)
+        } else if let Some(p) = Self::find_containing(self.partial, addr) {
+            (p, false)
+        } else {
+            return Err(AllocationError::InvalidLayout);
+        };
+
+        let page = unsafe { page_ptr.as_mut() };
+        let idx = (addr - page_ptr.as_ptr() as usize) / self.size;
+        page.clear_allocated(idx);
+
+        if was_full {
+            Self::remove(&mut self.full, page_ptr);
+            Self::push(&mut self.partial, unsafe { &mut *page_ptr.as_ptr() });
+        }
+
+        if page.is_empty() {
+            Self::remove(&mut self.partial, page_ptr);
+            if let Some(pager) = self.pager {
+                let raw = unsafe { NonNull::new_unchecked(page_ptr.as_ptr() as *mut u8) };
+                pager.release_page(raw, P::SIZE);
+            } else {
+                Self::push(&mut self.empty, unsafe { &mut *page_ptr.as_ptr() });
+            }
+        }
+
+        trace!("SCAllocator({}) deallocated object at {:#x}", self.size, addr);
+        Ok(())
+    }
+}
+
+impl<'a> SCAllocator<'a, ObjectPage<'a>> {}
+impl<'a> SCAllocator<'a, LargeObjectPage<'a>> {}
@@ -1,111 +1,222 @@
 use std::prelude::v1::*;
-use std::mem::{transmute};
-use libc;
-use rand;
-use std::mem::size_of;
-
-// The types we want to test:
-use super::{ZoneAllocator, SlabPage, SlabPageMeta, SlabAllocator, SlabPageAllocator};
-
-#[cfg(target_arch="x86_64")]
-use x86::paging::{CACHE_LINE_SIZE, BASE_PAGE_SIZE};
-
-/// Page allocator based on mmap/munmap system calls for backing slab memory.
-struct MmapSlabAllocator;
-
-/// mmap/munmap page allocator implementation.
-impl<'a> SlabPageAllocator<'a> for MmapSlabAllocator {
-    fn allocate_slabpage(&self) -> Option<&'a mut SlabPage<'a>> {
-        let mut addr: libc::c_void = libc::c_void::__variant1;
-        let len: libc::size_t = BASE_PAGE_SIZE;
-        let prot = libc::PROT_READ | libc::PROT_WRITE;
-        let flags = libc::MAP_PRIVATE | libc::MAP_ANON;
-        let fd = -1;
-        let offset = 0;
-        let r = unsafe { libc::mmap(&mut addr, len as libc::size_t, prot, flags, fd, offset) };
-        if r == libc::MAP_FAILED {
-            panic!("mmap failed!");
-            return None;
-        }
-        else {
-            let mut slab_page: &'a mut SlabPage = unsafe { transmute(r as usize) };
-            return Some(slab_page);
-        }
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use core::alloc::Layout;
+use core::ptr::NonNull;
+
+use super::{Allocator, AllocationError, ObjectPage, PageSource, SCAllocator, ZoneAllocator};
+
+/// A `PageSource` backed by the system (`std`) allocator, used only to
+/// exercise the self-refilling path in tests without depending on the
+/// `std`/`unix`-gated `MmapPageSource`. Counts calls so tests can assert on
+/// how many pages were actually pulled from / handed back to the source.
+#[derive(Default)]
+struct CountingPageSource {
+    allocated: AtomicUsize,
+    released: AtomicUsize,
+}
+
+unsafe impl PageSource for CountingPageSource {
+    fn allocate_page(&self, size: usize) -> Option<NonNull<u8>> {
+        let layout = Layout::from_size_align(size, size).unwrap();
+        self.allocated.fetch_add(1, Ordering::SeqCst);
+        NonNull::new(unsafe { std::alloc::alloc(layout) })
     }
 
-    fn release_slabpage(&self, p: &'a SlabPage) {
-        let addr: *mut libc::c_void = unsafe { transmute(p) };
-        let len: libc::size_t = BASE_PAGE_SIZE;
-        let r = unsafe { libc::munmap(addr, len) };
-        if r != 0 {
-            panic!("munmap failed!");
-        }
+    fn release_page(&self, ptr: NonNull<u8>, size: usize) {
+        let layout = Layout::from_size_align(size, size).unwrap();
+        self.released.fetch_add(1, Ordering::SeqCst);
+        unsafe { std::alloc::dealloc(ptr.as_ptr(), layout) };
+    }
+}
+
+#[test]
+fn sc_allocator_self_refills_and_returns_empty_pages() {
+    let pager = CountingPageSource::default();
+    let layout = Layout::from_size_align(8, 8).unwrap();
+    let mut sc: SCAllocator<ObjectPage> = SCAllocator::new(8);
+    sc.set_page_source(&pager);
+
+    let obj_per_page = (super::OBJECT_PAGE_SIZE - super::OBJECT_PAGE_METADATA_OVERHEAD) / 8;
+    let mut ptrs = Vec::new();
+    for _ in 0..obj_per_page {
+        ptrs.push(sc.allocate(layout).expect("page should self-refill from pager"));
     }
+    assert_eq!(pager.allocated.load(Ordering::SeqCst), 1);
+
+    // The first page is now full: this allocation has to pull a second one.
+    ptrs.push(sc.allocate(layout).expect("should self-refill a second page"));
+    assert_eq!(pager.allocated.load(Ordering::SeqCst), 2);
 
+    for ptr in ptrs.drain(..obj_per_page) {
+        sc.deallocate(ptr, layout).unwrap();
+    }
+    // The first page went fully empty and should have been handed straight
+    // back to the pager rather than hoarded.
+    assert_eq!(pager.released.load(Ordering::SeqCst), 1);
 }
 
 #[test]
-fn type_size() {
-    assert!(CACHE_LINE_SIZE as usize == size_of::<SlabPageMeta>(),
-               "Meta-data within page should not be larger than a single cache-line.");
-    assert!(BASE_PAGE_SIZE as usize == size_of::<SlabPage>(),
-               "SlabPage should be exactly the size of a single page.");
+fn zone_allocator_self_refills_via_page_source() {
+    let pager = CountingPageSource::default();
+    let mut zone = ZoneAllocator::with_page_source(&pager);
+    let layout = Layout::from_size_align(8, 8).unwrap();
+
+    let ptr = zone
+        .allocate(layout)
+        .expect("ZoneAllocator should self-refill with no manual `refill` call");
+    zone.deallocate(ptr, layout).unwrap();
+    assert_eq!(pager.allocated.load(Ordering::SeqCst), 1);
 }
 
 #[test]
-fn test_mmap_allocator() {
-    let mmap = MmapSlabAllocator;
-    match mmap.allocate_slabpage() {
-        Some(sp) =>  {
-            assert!(!sp.is_full(), "Got empty slab");
-            mmap.release_slabpage(sp)
-        },
-        None => panic!("failed to allocate slabpage")
-    }
+fn zone_allocator_returns_overaligned_pointer() {
+    let pager = CountingPageSource::default();
+    let mut zone = ZoneAllocator::with_page_source(&pager);
+    let layout = Layout::from_size_align(8, 64).unwrap();
+
+    let ptr = zone.allocate(layout).expect("64-byte alignment is well within a small class");
+    assert_eq!(ptr.as_ptr() as usize % 64, 0);
+    zone.deallocate(ptr, layout).unwrap();
 }
 
 #[test]
-fn test_slab_allocation4096_size8_alignment1() {
-    let mmap = MmapSlabAllocator;
-    let mut sa: SlabAllocator = SlabAllocator{
-        size: 8,
-        pager: &mmap,
-        allocateable_elements: 0,
-        allocateable: None,
-    };
-    let alignment = 1;
-
-    let mut objects: Vec<*mut u8> = Vec::new();
-    let mut vec: Vec<(usize, &mut [usize; 1])> = Vec::new();
-
-    for i in 0..4096 {
-        match sa.allocate(alignment) {
-            None => panic!("OOM is unlikely."),
-            Some(obj) => {
-                unsafe { vec.push( (rand::random::<usize>(), transmute(obj)) ) };
-                objects.push(obj)
-            }
+fn zone_allocator_serves_large_alignment_from_big_slab() {
+    let pager = CountingPageSource::default();
+    let mut zone = ZoneAllocator::with_page_source(&pager);
+    // Bigger than any small class's alignment cap, but well within what a
+    // single-object-per-page `LargeObjectPage` can provide.
+    let layout = Layout::from_size_align(3000, 8192).unwrap();
+
+    let ptr = zone
+        .allocate(layout)
+        .expect("big_slab should satisfy alignments beyond OBJECT_PAGE_SIZE");
+    assert_eq!(ptr.as_ptr() as usize % 8192, 0);
+    zone.deallocate(ptr, layout).unwrap();
+}
+
+#[test]
+fn zone_allocator_rejects_alignment_no_page_can_provide() {
+    let mut zone = ZoneAllocator::new();
+    let layout = Layout::from_size_align(8, 4 * super::LARGE_OBJECT_PAGE_SIZE).unwrap();
+    assert!(matches!(zone.allocate(layout), Err(AllocationError::InvalidLayout)));
+    assert_eq!(zone.allocated_size(layout), 0);
+}
+
+#[cfg(all(feature = "std", unix))]
+mod mmap_page_source {
+    use std::vec::Vec;
+
+    use core::alloc::Layout;
+    use core::slice;
+
+    use super::super::{MmapPageSource, PageSource, LARGE_OBJECT_PAGE_SIZE, OBJECT_PAGE_SIZE};
+
+    #[test]
+    fn allocates_a_usable_zeroed_page() {
+        let mmap = MmapPageSource::new();
+        let page = mmap
+            .allocate_page(OBJECT_PAGE_SIZE)
+            .expect("mmap should be able to back a single page");
+
+        // The page is ours to write to for its full size.
+        let bytes = unsafe { slice::from_raw_parts_mut(page.as_ptr(), OBJECT_PAGE_SIZE) };
+        assert!(bytes.iter().all(|&b| b == 0), "anonymous mmap should be zero-filled");
+        for b in bytes.iter_mut() {
+            *b = 0xAB;
         }
+        assert!(bytes.iter().all(|&b| b == 0xAB));
+
+        mmap.release_page(page, OBJECT_PAGE_SIZE);
     }
 
-    // Write the objects with a random pattern
-    for (idx, item) in vec.iter_mut().enumerate() {
-        let (pattern, ref mut obj) = *item;
-        for i in 0..obj.len() {
-            obj[i] = pattern;
+    #[test]
+    fn pages_are_distinct_and_dont_overlap() {
+        let mmap = MmapPageSource::new();
+        let mut pages = Vec::new();
+        for i in 0..8 {
+            let page = mmap
+                .allocate_page(OBJECT_PAGE_SIZE)
+                .unwrap_or_else(|| panic!("mmap failed for page {}", i));
+            (unsafe { slice::from_raw_parts_mut(page.as_ptr(), OBJECT_PAGE_SIZE) })[0] = i as u8;
+            pages.push(page);
+        }
+        for (i, page) in pages.iter().enumerate() {
+            let byte = (unsafe { slice::from_raw_parts(page.as_ptr(), OBJECT_PAGE_SIZE) })[0];
+            assert_eq!(byte, i as u8, "page {} was clobbered by another mapping", i);
+        }
+        for page in pages {
+            mmap.release_page(page, OBJECT_PAGE_SIZE);
         }
     }
 
-    // No two allocations point to the same memory:
-    for (idx, item) in vec.iter().enumerate() {
-        let (pattern, ref obj) = *item;
-        for i in 0..obj.len() {
-            assert!( (obj[i]) == pattern);
-        }
+    // Exercises `MmapPageSource` through the same `ZoneAllocator` path a real
+    // `#[global_allocator]` would use, end to end.
+    #[test]
+    fn zone_allocator_over_mmap_page_source() {
+        use super::super::{Allocator, ZoneAllocator};
+
+        let mmap = MmapPageSource::new();
+        let mut zone = ZoneAllocator::with_page_source(&mmap);
+        let layout = Layout::from_size_align(8, 8).unwrap();
+
+        let ptr = zone.allocate(layout).expect("should self-refill from mmap");
+        zone.deallocate(ptr, layout).unwrap();
     }
 
-    // Deallocate all the objects:
-    for item in objects.iter_mut() {
-        sa.deallocate(*item);
+    // The `MAP_HUGETLB` path is already aligned by the kernel; this exercises
+    // the plain-`mmap` fallback (taken whenever huge pages aren't available),
+    // which relies on `MmapPageSource::mmap_aligned`'s over-map-and-trim to
+    // get 2 MiB alignment out of an allocator only specified to be
+    // page-aligned.
+    #[test]
+    fn large_page_is_aligned_to_its_own_size() {
+        let mmap = MmapPageSource::new();
+        let page = mmap
+            .allocate_page(LARGE_OBJECT_PAGE_SIZE)
+            .expect("mmap should be able to back a large page");
+
+        assert_eq!(page.as_ptr() as usize % LARGE_OBJECT_PAGE_SIZE, 0);
+
+        // The full size is ours to write to, not just the part before
+        // whatever got trimmed off as over-mapped slack.
+        let bytes = unsafe { slice::from_raw_parts_mut(page.as_ptr(), LARGE_OBJECT_PAGE_SIZE) };
+        bytes[0] = 0xAB;
+        bytes[LARGE_OBJECT_PAGE_SIZE - 1] = 0xCD;
+
+        mmap.release_page(page, LARGE_OBJECT_PAGE_SIZE);
+    }
+
+    #[test]
+    fn allocate_oversized_respects_size_and_alignment() {
+        let mmap = MmapPageSource::new();
+        let layout = Layout::from_size_align(3 * LARGE_OBJECT_PAGE_SIZE, 4096).unwrap();
+
+        let ptr = mmap
+            .allocate_oversized(layout)
+            .expect("mmap should be able to back a multi-page oversized request");
+        assert_eq!(ptr.as_ptr() as usize % layout.align(), 0);
+
+        let bytes = unsafe { slice::from_raw_parts_mut(ptr.as_ptr(), layout.size()) };
+        bytes[0] = 0xAB;
+        bytes[layout.size() - 1] = 0xCD;
+
+        mmap.release_oversized(ptr, layout);
+    }
+
+    #[test]
+    fn locked_heap_serves_oversized_allocations_via_pager() {
+        use super::super::LockedHeap;
+        use core::alloc::GlobalAlloc;
+
+        let heap = LockedHeap::new(MmapPageSource::new());
+        let layout = Layout::from_size_align(3 * LARGE_OBJECT_PAGE_SIZE, 4096).unwrap();
+
+        let ptr = unsafe { heap.alloc(layout) };
+        assert!(!ptr.is_null(), "oversized allocation should fall through to the pager");
+        unsafe {
+            slice::from_raw_parts_mut(ptr, layout.size())[0] = 0x42;
+            heap.dealloc(ptr, layout);
+        }
     }
 }
@@ -0,0 +1,197 @@
+//! A `ZoneAllocator` dispatches allocation requests to the `SCAllocator`
+//! handling the corresponding size-class.
+
+use core::alloc::Layout;
+use core::ptr::NonNull;
+
+use crate::{
+    Allocator, AllocationError, LargeObjectPage, ObjectPage, PageSource, SCAllocator,
+};
+
+/// The size classes (in bytes) that small allocations are rounded up to.
+///
+/// Anything bigger than the last entry is served out of a
+/// [`LargeObjectPage`] instead, up to that page's single slot of
+/// `LARGE_OBJECT_PAGE_SIZE - OBJECT_PAGE_METADATA_OVERHEAD` bytes.
+const SIZE_CLASSES: [usize; 9] = [8, 16, 32, 64, 128, 256, 512, 1024, 2048];
+
+/// Manages a `SCAllocator` per size-class and routes allocation requests to
+/// the smallest one that can satisfy them.
+pub struct ZoneAllocator<'a> {
+    small_slabs: [SCAllocator<'a, ObjectPage<'a>>; SIZE_CLASSES.len()],
+    big_slab: SCAllocator<'a, LargeObjectPage<'a>>,
+}
+
+unsafe impl<'a> Send for ZoneAllocator<'a> {}
+
+impl<'a> Default for ZoneAllocator<'a> {
+    fn default() -> ZoneAllocator<'a> {
+        ZoneAllocator::new()
+    }
+}
+
+impl<'a> ZoneAllocator<'a> {
+    pub const fn new() -> ZoneAllocator<'a> {
+        ZoneAllocator {
+            small_slabs: [
+                SCAllocator::new(SIZE_CLASSES[0]),
+                SCAllocator::new(SIZE_CLASSES[1]),
+                SCAllocator::new(SIZE_CLASSES[2]),
+                SCAllocator::new(SIZE_CLASSES[3]),
+                SCAllocator::new(SIZE_CLASSES[4]),
+                SCAllocator::new(SIZE_CLASSES[5]),
+                SCAllocator::new(SIZE_CLASSES[6]),
+                SCAllocator::new(SIZE_CLASSES[7]),
+                SCAllocator::new(SIZE_CLASSES[8]),
+            ],
+            big_slab: SCAllocator::new(crate::LARGE_OBJECT_PAGE_SIZE - crate::OBJECT_PAGE_METADATA_OVERHEAD),
+        }
+    }
+
+    /// Creates a `ZoneAllocator` that pulls fresh backing pages from `pager`
+    /// itself once it runs out of room, instead of requiring callers to
+    /// call `refill`/`refill_large`.
+    pub fn with_page_source(pager: &'a dyn PageSource) -> ZoneAllocator<'a> {
+        let mut zone = ZoneAllocator::new();
+        for slab in zone.small_slabs.iter_mut() {
+            slab.set_page_source(pager);
+        }
+        zone.big_slab.set_page_source(pager);
+        zone
+    }
+
+    /// Returns the index into `small_slabs` that should serve a request of
+    /// `size` bytes aligned to `align`, or `None` if the request has to go
+    /// to `big_slab` instead.
+    ///
+    /// Every entry in `SIZE_CLASSES` is itself a power of two, so a class is
+    /// a multiple of `align` exactly when it is `>= align`; picking the
+    /// smallest class that is both `>= size` and `>= align` is therefore
+    /// enough to guarantee every pointer handed out of it satisfies the
+    /// requested alignment.
+    fn class_index(size: usize, align: usize) -> Option<usize> {
+        let min_class_size = size.max(align);
+        SIZE_CLASSES
+            .iter()
+            .position(|&class_size| min_class_size <= class_size)
+    }
+
+    /// Checked wrapper around `class_index` that rejects layouts no page
+    /// can satisfy: sizes bigger than `big_slab`'s single slot, and the
+    /// alignments described below.
+    ///
+    /// `class_index` only returns `Some` (routing to an `ObjectPage`-backed
+    /// small slab) when `align <= 2048`, well under `OBJECT_PAGE_SIZE`, so
+    /// the 4 KiB cap is automatically respected on that path. A `None`
+    /// instead routes to `big_slab`: a `LargeObjectPage` holds exactly one
+    /// object at offset zero, so its single slot satisfies any alignment up
+    /// to the page's own `LARGE_OBJECT_PAGE_SIZE` alignment — only beyond
+    /// that is the request impossible to satisfy.
+    fn checked_class_index(layout: Layout) -> Result<Option<usize>, AllocationError> {
+        if layout.align() > crate::LARGE_OBJECT_PAGE_SIZE {
+            return Err(AllocationError::InvalidLayout);
+        }
+        if layout.size() > crate::LARGE_OBJECT_PAGE_SIZE - crate::OBJECT_PAGE_METADATA_OVERHEAD {
+            return Err(AllocationError::InvalidLayout);
+        }
+        Ok(Self::class_index(layout.size(), layout.align()))
+    }
+
+    /// The size of the backing page that `refill`/`refill_large` needs in
+    /// order to satisfy a future allocation of `layout`.
+    pub(crate) fn page_size_for(layout: Layout) -> usize {
+        if Self::class_index(layout.size(), layout.align()).is_some() {
+            crate::OBJECT_PAGE_SIZE
+        } else {
+            crate::LARGE_OBJECT_PAGE_SIZE
+        }
+    }
+
+    /// Inserts a fresh, empty `ObjectPage` into the size-class matching
+    /// `layout`.
+    ///
+    /// # Safety
+    /// `page` must outlive every allocation handed out of it.
+    pub unsafe fn refill(
+        &mut self,
+        layout: Layout,
+        page: &'a mut ObjectPage<'a>,
+    ) -> Result<(), AllocationError> {
+        let idx = Self::checked_class_index(layout)?.ok_or(AllocationError::InvalidLayout)?;
+        self.small_slabs[idx].insert_page(page);
+        Ok(())
+    }
+
+    /// Inserts a fresh, empty `LargeObjectPage` into the big size-class.
+    ///
+    /// # Safety
+    /// `page` must outlive every allocation handed out of it.
+    pub unsafe fn refill_large(
+        &mut self,
+        _layout: Layout,
+        page: &'a mut LargeObjectPage<'a>,
+    ) -> Result<(), AllocationError> {
+        self.big_slab.insert_page(page);
+        Ok(())
+    }
+}
+
+/// Returns a well-aligned, non-null dangling pointer for a zero-sized
+/// [`Layout`], per the standard `GlobalAlloc`/`Allocator` convention:
+/// `layout.align()` is always a non-zero power of two, so it is itself a
+/// valid (if un-dereferenceable) pointer value for the requested alignment.
+fn dangling(layout: Layout) -> NonNull<u8> {
+    unsafe { NonNull::new_unchecked(layout.align() as *mut u8) }
+}
+
+unsafe impl<'a> Allocator<'a> for ZoneAllocator<'a> {
+    fn allocated_size(&self, layout: Layout) -> usize {
+        if layout.size() == 0 {
+            return 0;
+        }
+        match Self::checked_class_index(layout) {
+            Ok(Some(idx)) => SIZE_CLASSES[idx],
+            Ok(None) => self.big_slab.size,
+            // `allocate`/`deallocate` would reject this layout outright, so
+            // there is no usable size to report for it.
+            Err(_) => 0,
+        }
+    }
+
+    fn allocate(&mut self, layout: Layout) -> Result<NonNull<u8>, AllocationError> {
+        if layout.size() == 0 {
+            return Ok(dangling(layout));
+        }
+        match Self::checked_class_index(layout)? {
+            Some(idx) => self.small_slabs[idx].allocate(layout),
+            None => self.big_slab.allocate(layout),
+        }
+    }
+
+    fn deallocate(&mut self, ptr: NonNull<u8>, layout: Layout) -> Result<(), AllocationError> {
+        if layout.size() == 0 {
+            // Came from `dangling` above; it belongs to no `AllocablePage`.
+            return Ok(());
+        }
+        match Self::checked_class_index(layout)? {
+            Some(idx) => self.small_slabs[idx].deallocate(ptr, layout),
+            None => self.big_slab.deallocate(ptr, layout),
+        }
+    }
+
+    unsafe fn refill_large(
+        &mut self,
+        layout: Layout,
+        new_page: &'a mut LargeObjectPage<'a>,
+    ) -> Result<(), AllocationError> {
+        self.refill_large(layout, new_page)
+    }
+
+    unsafe fn refill(
+        &mut self,
+        layout: Layout,
+        new_page: &'a mut ObjectPage<'a>,
+    ) -> Result<(), AllocationError> {
+        self.refill(layout, new_page)
+    }
+}